@@ -8,10 +8,26 @@ pub struct VectorDocument {
     pub metadata: Option<HashMap<String, String>>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct KeyMap {
+    pub next_id: u64,
+    pub ids: HashMap<String, u64>,
+    #[serde(skip)]
+    pub keys: HashMap<u64, String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Storage {
     pub path: String,
     pub data: HashMap<String, VectorDocument>,
+    pub key_map: KeyMap,
+}
+
+#[derive(Debug, Clone)]
+pub struct KvStorage {
+    pub path: String,
+    pub data: HashMap<String, VectorDocument>,
+    pub key_map: KeyMap,
 }
 
 #[derive(Debug, Deserialize)]
@@ -0,0 +1,250 @@
+use crate::storage::{assign_id, StoreData};
+use configs::types::{KeyMap, KvStorage, VectorDocument};
+use serde::{Deserialize, Serialize};
+use serde_json;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const NEXT_ID_FILE: &str = "next_id";
+
+// One file per document, so a write only touches the key being changed
+// instead of rewriting the whole store like the JSON `Storage` does.
+#[derive(Serialize, Deserialize)]
+struct Record {
+    id: u64,
+    document: VectorDocument,
+}
+
+// Records are named by their assigned id so the file name length does not
+// depend on the key; the key itself lives inside the record.
+fn record_path(path: &str, id: u64) -> PathBuf {
+    PathBuf::from(path).join(format!("{}.json", id))
+}
+
+// Write to a synced sibling temp file and rename it into place, then sync
+// the directory so the rename itself survives a power loss.
+fn write_atomic(path: &Path, contents: &str) {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let mut file = File::create(&temp).unwrap();
+    file.write_all(contents.as_bytes()).unwrap();
+    file.sync_all().unwrap();
+    fs::rename(&temp, path).unwrap();
+    #[cfg(unix)]
+    if let Some(dir) = path.parent() {
+        File::open(dir).unwrap().sync_all().unwrap();
+    }
+}
+
+fn write_record(storage: &mut KvStorage, document: VectorDocument) {
+    let next_id = storage.key_map.next_id;
+    let id = assign_id(&mut storage.key_map, &document.key);
+    let record = Record { id, document };
+    write_atomic(
+        &record_path(&storage.path, record.id),
+        &serde_json::to_string(&record).unwrap(),
+    );
+    if storage.key_map.next_id != next_id {
+        write_atomic(
+            &PathBuf::from(&storage.path).join(NEXT_ID_FILE),
+            &storage.key_map.next_id.to_string(),
+        );
+    }
+    storage
+        .data
+        .insert(record.document.key.clone(), record.document);
+}
+
+impl StoreData for KvStorage {
+    fn working() -> &'static str {
+        "Hello, world! from package storage (kv)"
+    }
+
+    fn new(path: &str) -> Self {
+        println!("Creating new kv storage at path: {}", path);
+        fs::create_dir_all(path).unwrap();
+        let mut storage = KvStorage {
+            path: path.to_string(),
+            data: HashMap::new(),
+            key_map: KeyMap::default(),
+        };
+        // Records already on disk own their ids, so pick them up before any
+        // write can hand one of those ids to a different key.
+        storage.load();
+        storage
+    }
+
+    fn save(&self) {
+        println!("Documents are written per key; nothing to save");
+    }
+
+    fn load(&mut self) {
+        println!("Loading kv storage from directory");
+        self.data.clear();
+        self.key_map = KeyMap::default();
+        for entry in fs::read_dir(&self.path).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let record: Record = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+            let key = record.document.key.clone();
+            self.key_map.ids.insert(key.clone(), record.id);
+            self.key_map.keys.insert(record.id, key.clone());
+            self.key_map.next_id = self.key_map.next_id.max(record.id + 1);
+            self.data.insert(key, record.document);
+        }
+        if let Ok(next_id) = fs::read_to_string(PathBuf::from(&self.path).join(NEXT_ID_FILE)) {
+            let next_id: u64 = next_id.trim().parse().unwrap();
+            self.key_map.next_id = self.key_map.next_id.max(next_id);
+        }
+    }
+
+    fn store_vector(&mut self, document: VectorDocument) {
+        println!("Storing vector document with key: {}", document.key);
+        write_record(self, document);
+    }
+
+    fn retrieve_vector(&self, key: &str) -> Option<&VectorDocument> {
+        println!("Retrieving vector document with key: {}", key);
+        self.data.get(key)
+    }
+
+    fn update_vector(&mut self, document: VectorDocument) {
+        println!("Updating vector document with key: {}", document.key);
+        write_record(self, document);
+    }
+
+    fn delete_document(&mut self, key: &str) {
+        println!("Deleting vector document with key: {}", key);
+        self.data.remove(key);
+        if let Some(id) = self.key_map.ids.remove(key) {
+            self.key_map.keys.remove(&id);
+            fs::remove_file(record_path(&self.path, id)).unwrap();
+        }
+    }
+
+    fn list_documents(&self) -> Vec<&VectorDocument> {
+        println!("Listing all vector documents");
+        self.data.values().collect()
+    }
+
+    fn search_documents(&self, query: &str) -> Vec<&VectorDocument> {
+        println!("Searching for documents with query: {}", query);
+        self.data
+            .values()
+            .filter(|doc| doc.key.contains(query))
+            .collect()
+    }
+
+    fn key_for_id(&self, id: u64) -> Option<&str> {
+        self.key_map.keys.get(&id).map(|key| key.as_str())
+    }
+
+    fn id_for_key(&self, key: &str) -> Option<u64> {
+        self.key_map.ids.get(key).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rvector-kv-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn document(key: &str, value: f64) -> VectorDocument {
+        VectorDocument {
+            key: key.to_string(),
+            vector: vec![value, 0.0],
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn documents_survive_reopen() {
+        let dir = temp_dir("reopen");
+        let mut storage = KvStorage::new(dir.to_str().unwrap());
+        storage.store_vector(document("a", 1.0));
+        storage.store_vector(document("b", 2.0));
+        storage.store_vector(document("c", 3.0));
+        storage.update_vector(document("a", 9.0));
+        storage.delete_document("b");
+        drop(storage);
+
+        let mut reopened = KvStorage::new(dir.to_str().unwrap());
+        reopened.load();
+        assert_eq!(
+            reopened.retrieve_vector("a").unwrap().vector,
+            vec![9.0, 0.0]
+        );
+        assert_eq!(
+            reopened.retrieve_vector("c").unwrap().vector,
+            vec![3.0, 0.0]
+        );
+        assert!(reopened.retrieve_vector("b").is_none());
+        assert_eq!(reopened.id_for_key("a"), Some(0));
+        assert_eq!(reopened.id_for_key("b"), None);
+        assert_eq!(reopened.id_for_key("c"), Some(2));
+        assert_eq!(reopened.key_for_id(2), Some("c"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn ids_are_not_reused_after_deleting_the_highest() {
+        let dir = temp_dir("next-id");
+        let mut storage = KvStorage::new(dir.to_str().unwrap());
+        storage.store_vector(document("a", 1.0));
+        storage.store_vector(document("b", 2.0));
+        storage.delete_document("b");
+        drop(storage);
+
+        let mut reopened = KvStorage::new(dir.to_str().unwrap());
+        reopened.load();
+        reopened.store_vector(document("c", 3.0));
+        assert_eq!(reopened.id_for_key("c"), Some(2));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn new_on_existing_directory_keeps_records() {
+        let dir = temp_dir("existing");
+        let mut storage = KvStorage::new(dir.to_str().unwrap());
+        storage.store_vector(document("a", 1.0));
+        storage.store_vector(document("b", 2.0));
+        drop(storage);
+
+        let mut reopened = KvStorage::new(dir.to_str().unwrap());
+        reopened.store_vector(document("c", 3.0));
+        assert_eq!(reopened.id_for_key("c"), Some(2));
+        drop(reopened);
+
+        let mut loaded = KvStorage::new(dir.to_str().unwrap());
+        loaded.load();
+        assert_eq!(loaded.retrieve_vector("a").unwrap().vector, vec![1.0, 0.0]);
+        assert_eq!(loaded.retrieve_vector("b").unwrap().vector, vec![2.0, 0.0]);
+        assert_eq!(loaded.retrieve_vector("c").unwrap().vector, vec![3.0, 0.0]);
+        assert_eq!(loaded.id_for_key("a"), Some(0));
+        assert_eq!(loaded.id_for_key("b"), Some(1));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn long_keys_are_stored() {
+        let dir = temp_dir("long-key");
+        let key = "k".repeat(300);
+        let mut storage = KvStorage::new(dir.to_str().unwrap());
+        storage.store_vector(document(&key, 1.0));
+        drop(storage);
+
+        let mut reopened = KvStorage::new(dir.to_str().unwrap());
+        reopened.load();
+        assert!(reopened.retrieve_vector(&key).is_some());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1 +1,2 @@
+pub mod kv_storage;
 pub mod storage;
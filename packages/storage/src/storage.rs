@@ -1,4 +1,4 @@
-use configs::types::{Storage, VectorDocument};
+use configs::types::{KeyMap, Storage, VectorDocument};
 use serde::{Deserialize, Serialize};
 use serde_json;
 use std::collections::HashMap;
@@ -16,6 +16,138 @@ pub trait StoreData {
     fn delete_document(&mut self, key: &str);
     fn list_documents(&self) -> Vec<&VectorDocument>;
     fn search_documents(&self, query: &str) -> Vec<&VectorDocument>;
+    /// Pages are ordered by key ascending so they stay stable between calls.
+    fn list_documents_paged(&self, offset: usize, limit: usize) -> Vec<&VectorDocument> {
+        paginate(self.list_documents(), offset, limit)
+    }
+    /// Same ordering as `list_documents_paged`, applied to the search matches.
+    fn search_documents_paged(
+        &self,
+        query: &str,
+        offset: usize,
+        limit: usize,
+    ) -> Vec<&VectorDocument> {
+        paginate(self.search_documents(query), offset, limit)
+    }
+    fn search_by_metadata(&self, filters: &HashMap<String, String>) -> Vec<&VectorDocument> {
+        self.list_documents()
+            .into_iter()
+            .filter(|doc| matches_metadata(doc, filters))
+            .collect()
+    }
+    /// Filters by metadata first, then ranks the matches by cosine similarity, best first.
+    /// Documents whose vector length differs from `vector`, or whose score is
+    /// not finite because a component is NaN or infinite, are left out.
+    fn search_by_vector_and_metadata(
+        &self,
+        vector: &[f64],
+        filters: &HashMap<String, String>,
+    ) -> Vec<&VectorDocument> {
+        rank_by_similarity(self.search_by_metadata(filters), vector)
+    }
+    /// Pages keep the similarity ranking of `search_by_vector_and_metadata`.
+    fn search_by_vector_and_metadata_paged(
+        &self,
+        vector: &[f64],
+        filters: &HashMap<String, String>,
+        offset: usize,
+        limit: usize,
+    ) -> Vec<&VectorDocument> {
+        self.search_by_vector_and_metadata(vector, filters)
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .collect()
+    }
+    fn key_for_id(&self, id: u64) -> Option<&str>;
+    fn id_for_key(&self, key: &str) -> Option<u64>;
+}
+
+fn paginate(
+    mut documents: Vec<&VectorDocument>,
+    offset: usize,
+    limit: usize,
+) -> Vec<&VectorDocument> {
+    documents.sort_by(|a, b| a.key.cmp(&b.key));
+    documents.into_iter().skip(offset).take(limit).collect()
+}
+
+fn matches_metadata(document: &VectorDocument, filters: &HashMap<String, String>) -> bool {
+    filters.iter().all(|(key, value)| {
+        document
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get(key))
+            == Some(value)
+    })
+}
+
+// Both vectors are first scaled by their largest component. That leaves the
+// cosine unchanged but keeps the squares from overflowing for large values.
+fn cosine_similarity(a: &[f64], b: &[f64]) -> f64 {
+    let scale_a = a.iter().fold(0.0, |max: f64, x| max.max(x.abs()));
+    let scale_b = b.iter().fold(0.0, |max: f64, x| max.max(x.abs()));
+    if scale_a == 0.0 || scale_b == 0.0 {
+        return 0.0;
+    }
+    let (mut dot, mut norm_a, mut norm_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        let (x, y) = (x / scale_a, y / scale_b);
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+fn rank_by_similarity<'a>(
+    documents: Vec<&'a VectorDocument>,
+    vector: &[f64],
+) -> Vec<&'a VectorDocument> {
+    let mut scored: Vec<(f64, &VectorDocument)> = documents
+        .into_iter()
+        .filter(|doc| doc.vector.len() == vector.len())
+        .map(|doc| (cosine_similarity(&doc.vector, vector), doc))
+        .filter(|(score, _)| score.is_finite())
+        .collect();
+    scored.sort_by(|(score_a, doc_a), (score_b, doc_b)| {
+        score_b
+            .total_cmp(score_a)
+            .then_with(|| doc_a.key.cmp(&doc_b.key))
+    });
+    scored.into_iter().map(|(_, doc)| doc).collect()
+}
+
+const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct SnapshotRef<'a> {
+    version: u32,
+    data: &'a HashMap<String, VectorDocument>,
+    key_map: &'a KeyMap,
+}
+
+// Files written before the key map existed hold the bare document map.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Snapshot {
+    Versioned {
+        version: u32,
+        data: HashMap<String, VectorDocument>,
+        key_map: KeyMap,
+    },
+    Legacy(HashMap<String, VectorDocument>),
+}
+
+pub(crate) fn assign_id(key_map: &mut KeyMap, key: &str) -> u64 {
+    if let Some(&id) = key_map.ids.get(key) {
+        return id;
+    }
+    let id = key_map.next_id;
+    key_map.next_id += 1;
+    key_map.ids.insert(key.to_string(), id);
+    key_map.keys.insert(id, key.to_string());
+    id
 }
 
 impl StoreData for Storage {
@@ -28,15 +160,22 @@ impl StoreData for Storage {
         Storage {
             path: path.to_string(),
             data: HashMap::new(),
+            key_map: KeyMap::default(),
         }
     }
 
     fn save(&self) {
         println!("Saving storage to file");
-        let serialized = serde_json::to_string(&self.data).unwrap();
+        let snapshot = SnapshotRef {
+            version: SNAPSHOT_VERSION,
+            data: &self.data,
+            key_map: &self.key_map,
+        };
+        let serialized = serde_json::to_string(&snapshot).unwrap();
         let mut file = OpenOptions::new()
             .write(true)
             .create(true)
+            .truncate(true)
             .open(&self.path)
             .unwrap();
         file.write_all(serialized.as_bytes()).unwrap();
@@ -47,11 +186,41 @@ impl StoreData for Storage {
         let mut file = OpenOptions::new().read(true).open(&self.path).unwrap();
         let mut content = String::new();
         file.read_to_string(&mut content).unwrap();
-        self.data = serde_json::from_str(&content).unwrap();
+        match serde_json::from_str(&content).unwrap() {
+            Snapshot::Versioned {
+                version,
+                data,
+                key_map,
+            } => {
+                assert!(
+                    version <= SNAPSHOT_VERSION,
+                    "Unsupported storage file version: {}",
+                    version
+                );
+                self.data = data;
+                self.key_map = key_map;
+                self.key_map.keys = self
+                    .key_map
+                    .ids
+                    .iter()
+                    .map(|(key, &id)| (id, key.clone()))
+                    .collect();
+            }
+            Snapshot::Legacy(data) => {
+                let mut keys: Vec<&String> = data.keys().collect();
+                keys.sort();
+                self.key_map = KeyMap::default();
+                for key in keys {
+                    assign_id(&mut self.key_map, key);
+                }
+                self.data = data;
+            }
+        }
     }
 
     fn store_vector(&mut self, document: VectorDocument) {
         println!("Storing vector document with key: {}", document.key);
+        assign_id(&mut self.key_map, &document.key);
         self.data.insert(document.key.clone(), document);
     }
 
@@ -62,12 +231,16 @@ impl StoreData for Storage {
 
     fn update_vector(&mut self, document: VectorDocument) {
         println!("Updating vector document with key: {}", document.key);
+        assign_id(&mut self.key_map, &document.key);
         self.data.insert(document.key.clone(), document);
     }
 
     fn delete_document(&mut self, key: &str) {
         println!("Deleting vector document with key: {}", key);
         self.data.remove(key);
+        if let Some(id) = self.key_map.ids.remove(key) {
+            self.key_map.keys.remove(&id);
+        }
     }
 
     fn list_documents(&self) -> Vec<&VectorDocument> {
@@ -82,4 +255,190 @@ impl StoreData for Storage {
             .filter(|doc| doc.key.contains(query))
             .collect()
     }
+
+    fn key_for_id(&self, id: u64) -> Option<&str> {
+        self.key_map.keys.get(&id).map(|key| key.as_str())
+    }
+
+    fn id_for_key(&self, key: &str) -> Option<u64> {
+        self.key_map.ids.get(key).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("rvector-storage-{}-{}", name, std::process::id()))
+    }
+
+    fn document(key: &str) -> VectorDocument {
+        VectorDocument {
+            key: key.to_string(),
+            vector: vec![1.0, 0.0],
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn key_map_survives_save_and_load() {
+        let path = temp_path("key-map");
+        let mut storage = Storage::new(path.to_str().unwrap());
+        storage.store_vector(document("a"));
+        storage.store_vector(document("b"));
+        storage.delete_document("a");
+        storage.save();
+
+        let mut loaded = Storage::new(path.to_str().unwrap());
+        loaded.load();
+        assert_eq!(loaded.id_for_key("b"), Some(1));
+        assert_eq!(loaded.key_for_id(1), Some("b"));
+        assert_eq!(loaded.id_for_key("a"), None);
+        assert_eq!(loaded.key_for_id(0), None);
+
+        loaded.store_vector(document("c"));
+        assert_eq!(loaded.id_for_key("c"), Some(2));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn pages_are_ordered_by_key() {
+        let mut storage = Storage::new(temp_path("paged").to_str().unwrap());
+        for i in (0..25).rev() {
+            storage.store_vector(document(&format!("doc{:02}", i)));
+        }
+        let page: Vec<&str> = storage
+            .list_documents_paged(10, 10)
+            .iter()
+            .map(|doc| doc.key.as_str())
+            .collect();
+        let expected: Vec<String> = (10..20).map(|i| format!("doc{:02}", i)).collect();
+        assert_eq!(page, expected);
+        assert_eq!(storage.list_documents_paged(20, 10).len(), 5);
+        assert_eq!(storage.search_documents_paged("doc1", 5, 10).len(), 5);
+    }
+
+    #[test]
+    fn vector_search_pages_keep_ranking() {
+        let mut storage = Storage::new(temp_path("vector-paged").to_str().unwrap());
+        for i in 0..5 {
+            storage.store_vector(VectorDocument {
+                key: format!("doc{}", i),
+                vector: vec![1.0, i as f64],
+                metadata: None,
+            });
+        }
+        let page: Vec<&str> = storage
+            .search_by_vector_and_metadata_paged(&[0.0, 1.0], &HashMap::new(), 1, 2)
+            .iter()
+            .map(|doc| doc.key.as_str())
+            .collect();
+        assert_eq!(page, vec!["doc3", "doc2"]);
+    }
+
+    fn tagged(key: &str, vector: Vec<f64>, tags: &[(&str, &str)]) -> VectorDocument {
+        VectorDocument {
+            key: key.to_string(),
+            vector,
+            metadata: Some(
+                tags.iter()
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn filters(tags: &[(&str, &str)]) -> HashMap<String, String> {
+        tags.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn metadata_storage(name: &str) -> Storage {
+        let mut storage = Storage::new(temp_path(name).to_str().unwrap());
+        storage.store_vector(tagged(
+            "a",
+            vec![1.0, 0.0],
+            &[("lang", "en"), ("tenant", "x")],
+        ));
+        storage.store_vector(tagged(
+            "b",
+            vec![0.0, 1.0],
+            &[("lang", "en"), ("tenant", "x")],
+        ));
+        storage.store_vector(tagged(
+            "c",
+            vec![0.0, 1.0],
+            &[("lang", "fr"), ("tenant", "x")],
+        ));
+        storage.store_vector(tagged("d", vec![0.0, 1.0], &[("lang", "en")]));
+        storage.store_vector(document("e"));
+        storage
+    }
+
+    #[test]
+    fn metadata_search_matches_all_filters() {
+        let storage = metadata_storage("metadata");
+        let mut keys: Vec<&str> = storage
+            .search_by_metadata(&filters(&[("lang", "en"), ("tenant", "x")]))
+            .iter()
+            .map(|doc| doc.key.as_str())
+            .collect();
+        keys.sort();
+        assert_eq!(keys, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn vector_and_metadata_search_ranks_filtered_documents() {
+        let mut storage = metadata_storage("vector-metadata");
+        storage.store_vector(tagged(
+            "f",
+            vec![0.0, 1.0, 0.0],
+            &[("lang", "en"), ("tenant", "x")],
+        ));
+        let keys: Vec<&str> = storage
+            .search_by_vector_and_metadata(
+                &[0.1, 1.0],
+                &filters(&[("lang", "en"), ("tenant", "x")]),
+            )
+            .iter()
+            .map(|doc| doc.key.as_str())
+            .collect();
+        assert_eq!(keys, vec!["b", "a"]);
+    }
+
+    #[test]
+    fn vector_search_handles_large_and_non_finite_components() {
+        let mut storage = Storage::new(temp_path("large").to_str().unwrap());
+        storage.store_vector(tagged("a", vec![1.0, 0.0], &[]));
+        storage.store_vector(tagged("b", vec![1e200, 1e200], &[]));
+        storage.store_vector(tagged("c", vec![f64::NAN, 1.0], &[]));
+        storage.store_vector(tagged("d", vec![f64::INFINITY, 1.0], &[]));
+        let keys: Vec<&str> = storage
+            .search_by_vector_and_metadata(&[1.0, 1.0], &HashMap::new())
+            .iter()
+            .map(|doc| doc.key.as_str())
+            .collect();
+        assert_eq!(keys, vec!["b", "a"]);
+    }
+
+    #[test]
+    fn load_accepts_legacy_document_map() {
+        let path = temp_path("legacy");
+        let data: HashMap<String, VectorDocument> = ["b", "a"]
+            .iter()
+            .map(|key| (key.to_string(), document(key)))
+            .collect();
+        fs::write(&path, serde_json::to_string(&data).unwrap()).unwrap();
+
+        let mut storage = Storage::new(path.to_str().unwrap());
+        storage.load();
+        assert!(storage.retrieve_vector("a").is_some());
+        assert_eq!(storage.id_for_key("a"), Some(0));
+        assert_eq!(storage.id_for_key("b"), Some(1));
+        fs::remove_file(path).unwrap();
+    }
 }